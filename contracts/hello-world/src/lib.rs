@@ -1,5 +1,5 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, contracttype, Address, Env, Map, Symbol, Vec};
+use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Env, Map, Symbol, Vec};
 
 #[derive(Clone)]
 #[contracttype]
@@ -16,42 +16,202 @@ pub struct Group {
     member_shares: Map<Address, i128>,
 }
 
+// Ways a caller can describe how an expense should be split, resolved to
+// `SplitInfo`s before touching `member_shares`: basis points for
+// `Percentage`, exact per-member amounts for the other strategies.
+#[derive(Clone)]
+#[contracttype]
+pub enum SplitStrategy {
+    Equal,
+    Exact(Vec<i128>),
+    Shares(Vec<(Address, u32)>),
+    Percentage(Vec<SplitInfo>),
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Transfer {
+    from: Address,
+    to: Address,
+    amount: i128,
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub struct Expense {
     payer: Address,
     amount: i128,
+    asset: Symbol,
+    original_amount: i128,
     description: Symbol,
     split_info: Vec<SplitInfo>,
+    // When true, `split_info[i].share` is an exact amount rather than a
+    // basis-point percentage; see `record_expense`.
+    exact: bool,
     timestamp: u64,
 }
 
+// Bundles the per-expense fields `record_expense` only needs to copy onto
+// the stored `Expense`, keeping its own argument list from growing with
+// every field `Expense` gains.
+struct ExpenseDetails {
+    asset: Symbol,
+    original_amount: i128,
+    description: Symbol,
+    split_members: Vec<SplitInfo>,
+    exact: bool,
+}
+
 #[contracttype]
 pub enum DataKey {
     GroupCounter,
     Group(u32),
     MemberGroups(Address),
     GroupExpenses(u32),
+    SettlementToken(u32),
+    Admin,
+    ConversionRate(Symbol),
 }
 
+// Roughly one day's worth of ledgers at a 5 second close time.
+const DAY_IN_LEDGERS: u32 = 17280;
+// Groups are funded for ~30 days of inactivity at a time; any access bumps
+// them back to 30 days once less than 29 days remain.
+const GROUP_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const GROUP_BUMP_THRESHOLD: u32 = GROUP_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
 #[contract]
 pub struct SplitPayment;
 
 #[contractimpl]
 impl SplitPayment {
+    fn load_group(env: &Env, group_id: u32) -> Group {
+        let key = DataKey::Group(group_id);
+        let group = env
+            .storage()
+            .persistent()
+            .get(&key)
+            .unwrap_or_else(|| panic!("Group does not exist"));
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, GROUP_BUMP_THRESHOLD, GROUP_BUMP_AMOUNT);
+        group
+    }
+
+    fn save_group(env: &Env, group_id: u32, group: &Group) {
+        let key = DataKey::Group(group_id);
+        env.storage().persistent().set(&key, group);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, GROUP_BUMP_THRESHOLD, GROUP_BUMP_AMOUNT);
+    }
+
+    fn load_expenses(env: &Env, group_id: u32) -> Vec<Expense> {
+        let key = DataKey::GroupExpenses(group_id);
+        if env.storage().persistent().has(&key) {
+            env.storage()
+                .persistent()
+                .extend_ttl(&key, GROUP_BUMP_THRESHOLD, GROUP_BUMP_AMOUNT);
+        }
+        env.storage().persistent().get(&key).unwrap_or(Vec::new(env))
+    }
+
+    fn save_expenses(env: &Env, group_id: u32, expenses: &Vec<Expense>) {
+        let key = DataKey::GroupExpenses(group_id);
+        env.storage().persistent().set(&key, expenses);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, GROUP_BUMP_THRESHOLD, GROUP_BUMP_AMOUNT);
+    }
+
+    fn load_member_groups(env: &Env, member: &Address) -> Vec<u32> {
+        let key = DataKey::MemberGroups(member.clone());
+        if env.storage().persistent().has(&key) {
+            env.storage()
+                .persistent()
+                .extend_ttl(&key, GROUP_BUMP_THRESHOLD, GROUP_BUMP_AMOUNT);
+        }
+        env.storage().persistent().get(&key).unwrap_or(Vec::new(env))
+    }
+
+    fn save_member_groups(env: &Env, member: &Address, groups: &Vec<u32>) {
+        let key = DataKey::MemberGroups(member.clone());
+        env.storage().persistent().set(&key, groups);
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, GROUP_BUMP_THRESHOLD, GROUP_BUMP_AMOUNT);
+    }
+
+    // Let a client pay to keep an inactive group's data from being archived.
+    pub fn bump_group(env: Env, group_id: u32, ledgers: u32) {
+        let group_key = DataKey::Group(group_id);
+        if !env.storage().persistent().has(&group_key) {
+            panic!("Group does not exist");
+        }
+        env.storage().persistent().extend_ttl(&group_key, ledgers, ledgers);
+
+        let expenses_key = DataKey::GroupExpenses(group_id);
+        if env.storage().persistent().has(&expenses_key) {
+            env.storage()
+                .persistent()
+                .extend_ttl(&expenses_key, ledgers, ledgers);
+        }
+    }
+
+    // One-time setup of the admin allowed to register conversion rates.
+    pub fn initialize(env: Env, admin: Address) {
+        if env.storage().instance().has(&DataKey::Admin) {
+            panic!("Contract is already initialized");
+        }
+        env.storage().instance().set(&DataKey::Admin, &admin);
+    }
+
+    // Register how many base units one unit of `asset` is worth, expressed
+    // in basis points (e.g. 10000 for a 1:1 base asset, 10850 if 1 EUR is
+    // worth 1.085 base units). Expenses in an asset with no registered rate
+    // are rejected.
+    pub fn set_conversion_rate(env: Env, asset: Symbol, rate_bps: i128) {
+        let admin: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .unwrap_or_else(|| panic!("Contract has not been initialized"));
+        admin.require_auth();
+
+        if rate_bps <= 0 {
+            panic!("Conversion rate must be positive");
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::ConversionRate(asset), &rate_bps);
+    }
+
+    // Convert an amount denominated in `asset` into the group's base unit
+    // using the registered conversion rate, rejecting unregistered assets.
+    fn convert_to_base(env: &Env, asset: &Symbol, amount: i128) -> i128 {
+        let rate_bps: i128 = env
+            .storage()
+            .instance()
+            .get(&DataKey::ConversionRate(asset.clone()))
+            .unwrap_or_else(|| panic!("No conversion rate registered for this asset"));
+
+        (amount * rate_bps) / 10000
+    }
+
     pub fn create_group(env: Env, members: Vec<Address>) -> u32 {
         // Validate members list is not empty
         if members.len() == 0 {
             panic!("Group must have at least one member");
         }
 
-        // Initialize group counter if not exists
-        if !env.storage().instance().has(&0) {
-            env.storage().instance().set(&0, &0u32);
-        }
-
         // Generate new group ID
-        let group_id = env.storage().instance().get(&0).unwrap_or(0) + 1;
+        let group_id: u32 = env
+            .storage()
+            .instance()
+            .get(&DataKey::GroupCounter)
+            .unwrap_or(0)
+            + 1;
 
         // Create new group
         let group = Group {
@@ -61,27 +221,23 @@ impl SplitPayment {
         };
 
         // Store group
-        env.storage().instance().set(&group_id, &group);
-        env.storage().instance().set(&0, &group_id);
+        Self::save_group(&env, group_id, &group);
+        env.storage()
+            .instance()
+            .set(&DataKey::GroupCounter, &group_id);
 
         // Store group references for each member
         for member in members.iter() {
-            let mut member_groups: Vec<u32> = env
-                .storage()
-                .instance()
-                .get(&DataKey::MemberGroups(member.clone()))
-                .unwrap_or(Vec::new(&env));
+            let mut member_groups = Self::load_member_groups(&env, &member);
             member_groups.push_back(group_id);
-            env.storage()
-                .instance()
-                .set(&DataKey::MemberGroups(member), &member_groups);
+            Self::save_member_groups(&env, &member, &member_groups);
         }
 
         group_id
     }
 
     pub fn add_member(env: Env, group_id: u32, new_member: Address) {
-        let mut group: Group = env.storage().instance().get(&group_id).unwrap();
+        let mut group = Self::load_group(&env, group_id);
 
         // Check if member already exists
         if group.members.contains(&new_member) {
@@ -92,22 +248,16 @@ impl SplitPayment {
         group.members.push_back(new_member.clone());
 
         // Update group in storage
-        env.storage().instance().set(&group_id, &group);
+        Self::save_group(&env, group_id, &group);
 
         // Update member's group references
-        let mut member_groups: Vec<u32> = env
-            .storage()
-            .instance()
-            .get(&DataKey::MemberGroups(new_member.clone()))
-            .unwrap_or(Vec::new(&env));
+        let mut member_groups = Self::load_member_groups(&env, &new_member);
         member_groups.push_back(group_id);
-        env.storage()
-            .instance()
-            .set(&DataKey::MemberGroups(new_member), &member_groups);
+        Self::save_member_groups(&env, &new_member, &member_groups);
     }
 
     pub fn remove_member(env: Env, group_id: u32, member: Address) {
-        let mut group: Group = env.storage().instance().get(&group_id).unwrap();
+        let mut group = Self::load_group(&env, group_id);
 
         // Verify member exists
         if !group.members.contains(&member) {
@@ -125,24 +275,18 @@ impl SplitPayment {
         group.members.remove(idx);
 
         // Update group in storage
-        env.storage().instance().set(&group_id, &group);
+        Self::save_group(&env, group_id, &group);
 
         // Update member's group references
-        let member_groups_key = DataKey::MemberGroups(member.clone());
-        if let Some(member_groups) = env.storage().instance().get(&member_groups_key) {
-            let mut updated_groups: Vec<u32> = member_groups;
-            if let Some(idx) = updated_groups.first_index_of(&group_id) {
-                updated_groups.remove(idx);
-                env.storage()
-                    .instance()
-                    .set(&DataKey::MemberGroups(member), &updated_groups);
-            }
+        let mut member_groups = Self::load_member_groups(&env, &member);
+        if let Some(idx) = member_groups.first_index_of(&group_id) {
+            member_groups.remove(idx);
+            Self::save_member_groups(&env, &member, &member_groups);
         }
     }
 
     pub fn get_group_members(env: Env, group_id: u32) -> Vec<Address> {
-        let group: Group = env.storage().instance().get(&group_id).unwrap();
-        group.members
+        Self::load_group(&env, group_id).members
     }
 
     pub fn add_expense(
@@ -150,10 +294,87 @@ impl SplitPayment {
         group_id: u32,
         payer: Address,
         amount: i128,
+        asset: Symbol,
         description: Symbol,
         split_members: Vec<SplitInfo>,
     ) -> u32 {
-        let mut group: Group = env.storage().instance().get(&group_id).unwrap();
+        let group = Self::load_group(&env, group_id);
+        let base_amount = Self::convert_to_base(&env, &asset, amount);
+        Self::record_expense(
+            env,
+            group_id,
+            group,
+            payer,
+            base_amount,
+            ExpenseDetails {
+                asset,
+                original_amount: amount,
+                description,
+                split_members,
+                exact: false,
+            },
+        )
+    }
+
+    // Same as `add_expense`, but lets the caller describe the split with a
+    // `SplitStrategy` instead of pre-computing basis points by hand.
+    pub fn add_expense_with_strategy(
+        env: Env,
+        group_id: u32,
+        payer: Address,
+        amount: i128,
+        asset: Symbol,
+        description: Symbol,
+        strategy: SplitStrategy,
+    ) -> u32 {
+        let group = Self::load_group(&env, group_id);
+
+        if amount <= 0 {
+            panic!("Amount must be positive");
+        }
+
+        let base_amount = Self::convert_to_base(&env, &asset, amount);
+        let (split_members, exact) =
+            Self::strategy_to_splits(&env, &group, &payer, base_amount, strategy);
+        Self::record_expense(
+            env,
+            group_id,
+            group,
+            payer,
+            base_amount,
+            ExpenseDetails {
+                asset,
+                original_amount: amount,
+                description,
+                split_members,
+                exact,
+            },
+        )
+    }
+
+    // Shared bookkeeping for recording an expense once its split has been
+    // resolved: validates, stores the `Expense`, and updates the running
+    // balance of every member listed in the split. `amount` is already converted into the group's base
+    // unit; `asset`/`original_amount` are kept only for auditability. When
+    // `exact` is true, `split_members[i].share` is already an exact amount
+    // (as produced by the `Equal`/`Exact`/`Shares` strategies) and is applied
+    // directly, instead of being re-derived from basis points — avoiding a
+    // lossy bps round trip that can leave `member_shares` unbalanced.
+    fn record_expense(
+        env: Env,
+        group_id: u32,
+        mut group: Group,
+        payer: Address,
+        amount: i128,
+        details: ExpenseDetails,
+    ) -> u32 {
+        let ExpenseDetails {
+            asset,
+            original_amount,
+            description,
+            split_members,
+            exact,
+        } = details;
 
         // Verify payer is a member
         if !group.members.contains(&payer) {
@@ -172,9 +393,13 @@ impl SplitPayment {
             }
         }
 
-        // Verify total split equals 100%
+        // Verify the split accounts for the whole expense
         let total_split: i128 = split_members.iter().map(|s| s.share).sum();
-        if total_split != 10000 {
+        if exact {
+            if total_split != amount {
+                panic!("Split amounts must sum to the expense amount");
+            }
+        } else if total_split != 10000 {
             // 10000 basis points = 100%
             panic!("Split shares must total 100%");
         }
@@ -183,26 +408,28 @@ impl SplitPayment {
         let expense = Expense {
             payer: payer.clone(),
             amount,
+            asset,
+            original_amount,
             description,
             split_info: split_members.clone(),
+            exact,
             timestamp: env.ledger().timestamp(),
         };
 
         // Store expense
-        let expenses_key = DataKey::GroupExpenses(group_id);
-        let mut expenses: Vec<Expense> = env
-            .storage()
-            .instance()
-            .get(&expenses_key)
-            .unwrap_or(Vec::new(&env));
+        let mut expenses = Self::load_expenses(&env, group_id);
         expenses.push_back(expense);
-        env.storage().instance().set(&expenses_key, &expenses);
+        Self::save_expenses(&env, group_id, &expenses);
 
         // Update member shares
         for split in split_members.iter() {
             let member = split.member.clone();
             let current = group.member_shares.get(member.clone()).unwrap_or(0);
-            let member_share = (amount * split.share) / 10000;
+            let member_share = if exact {
+                split.share
+            } else {
+                (amount * split.share) / 10000
+            };
 
             if member == payer {
                 group
@@ -217,21 +444,109 @@ impl SplitPayment {
 
         // Update total amount
         group.total_amount += amount;
-        env.storage().instance().set(&group_id, &group);
+        Self::save_group(&env, group_id, &group);
 
         group_id
     }
 
+    // Resolve a `SplitStrategy` into `SplitInfo`s plus a flag telling
+    // `record_expense` how to interpret `share`. `Percentage` already arrives
+    // as basis points, so it's passed through unchanged (`exact = false`).
+    // `Equal`/`Exact`/`Shares` resolve to concrete per-member amounts that
+    // already sum to exactly `amount` (any remainder from integer division
+    // is credited to the payer; for `Shares` only when the payer is among the
+    // weighted members, otherwise the remainder is left out and
+    // `record_expense` rejects the split) — returned as-is (`exact = true`)
+    // rather than re-encoded as basis points, which would round-trip through
+    // two lossy floor divisions and could leave `member_shares` unbalanced.
+    fn strategy_to_splits(
+        env: &Env,
+        group: &Group,
+        payer: &Address,
+        amount: i128,
+        strategy: SplitStrategy,
+    ) -> (Vec<SplitInfo>, bool) {
+        match strategy {
+            SplitStrategy::Percentage(splits) => (splits, false),
+            SplitStrategy::Equal => {
+                let member_count = group.members.len() as i128;
+                let share = amount / member_count;
+                let remainder = amount - share * member_count;
+
+                let mut amounts: Vec<(Address, i128)> = Vec::new(env);
+                for member in group.members.iter() {
+                    let extra = if &member == payer { remainder } else { 0 };
+                    amounts.push_back((member, share + extra));
+                }
+
+                (Self::exact_amounts_to_splits(env, amounts), true)
+            }
+            SplitStrategy::Exact(exact_amounts) => {
+                if exact_amounts.len() != group.members.len() {
+                    panic!("Exact amounts must cover every group member");
+                }
+
+                let total: i128 = exact_amounts.iter().sum();
+                if total != amount {
+                    panic!("Exact amounts must sum to the expense amount");
+                }
+
+                let mut amounts: Vec<(Address, i128)> = Vec::new(env);
+                for i in 0..group.members.len() {
+                    let member = group.members.get(i).unwrap();
+                    let member_amount = exact_amounts.get(i).unwrap();
+                    amounts.push_back((member, member_amount));
+                }
+
+                (Self::exact_amounts_to_splits(env, amounts), true)
+            }
+            SplitStrategy::Shares(weights) => {
+                let total_weight: u32 = weights.iter().map(|(_, weight)| weight).sum();
+                if total_weight == 0 {
+                    panic!("Total shares must be greater than zero");
+                }
+
+                let mut allocated = 0i128;
+                let mut amounts: Vec<(Address, i128)> = Vec::new(env);
+                for (member, weight) in weights.iter() {
+                    let member_amount = (amount * weight as i128) / total_weight as i128;
+                    allocated += member_amount;
+                    amounts.push_back((member, member_amount));
+                }
+
+                let remainder = amount - allocated;
+                for i in 0..amounts.len() {
+                    let (member, member_amount) = amounts.get(i).unwrap();
+                    if &member == payer {
+                        amounts.set(i, (member, member_amount + remainder));
+                        break;
+                    }
+                }
+
+                (Self::exact_amounts_to_splits(env, amounts), true)
+            }
+        }
+    }
+
+    // Turn resolved per-member amounts into `SplitInfo`s whose `share` is the
+    // exact amount itself — no basis-point conversion, so nothing is lost to
+    // rounding.
+    fn exact_amounts_to_splits(env: &Env, amounts: Vec<(Address, i128)>) -> Vec<SplitInfo> {
+        let mut splits: Vec<SplitInfo> = Vec::new(env);
+        for (member, member_amount) in amounts.iter() {
+            splits.push_back(SplitInfo {
+                member,
+                share: member_amount,
+            });
+        }
+        splits
+    }
+
     pub fn remove_expense(env: Env, group_id: u32, expense_index: u32, authorized_by: Address) {
-        let mut group: Group = env.storage().instance().get(&group_id).unwrap();
+        let mut group = Self::load_group(&env, group_id);
 
         // Get expenses
-        let expenses_key = DataKey::GroupExpenses(group_id);
-        let mut expenses: Vec<Expense> = env
-            .storage()
-            .instance()
-            .get(&expenses_key)
-            .unwrap_or(Vec::new(&env));
+        let mut expenses = Self::load_expenses(&env, group_id);
 
         // Check if expense index is valid
         if expense_index as u32 >= expenses.len() {
@@ -250,7 +565,11 @@ impl SplitPayment {
         for split in expense.split_info.iter() {
             let member = split.member.clone();
             let current = group.member_shares.get(member.clone()).unwrap_or(0);
-            let member_share = (expense.amount * split.share) / 10000;
+            let member_share = if expense.exact {
+                split.share
+            } else {
+                (expense.amount * split.share) / 10000
+            };
 
             if member == expense.payer {
                 // For payer: subtract the full amount and subtract their share
@@ -274,20 +593,85 @@ impl SplitPayment {
         expenses.remove(expense_index);
 
         // Update storage
-        env.storage().instance().set(&group_id, &group);
-        env.storage().instance().set(&expenses_key, &expenses);
+        Self::save_group(&env, group_id, &group);
+        Self::save_expenses(&env, group_id, &expenses);
+    }
+
+    // Configure the SEP-41 token used to actually move value when settling
+    // this group's debts. Must be called before `settle_debt`/`settle_all`.
+    pub fn init_settlement_token(env: Env, group_id: u32, token: Address) {
+        Self::load_group(&env, group_id);
+
+        let token_key = DataKey::SettlementToken(group_id);
+        if env.storage().instance().has(&token_key) {
+            panic!("Settlement token is already set for this group");
+        }
+
+        env.storage().instance().set(&token_key, &token);
     }
 
     pub fn settle_debt(env: Env, group_id: u32, from: Address, to: Address, amount: i128) {
-        let mut group: Group = env.storage().instance().get(&group_id).unwrap();
+        let mut group = Self::load_group(&env, group_id);
+        let token = Self::settlement_token(&env, group_id);
+
+        Self::transfer_and_settle(&env, &mut group, &token, &from, &to, amount);
+
+        Self::save_group(&env, group_id, &group);
+    }
+
+    // Discharge `debtor`'s entire position in one authorized call by running
+    // the settlement-minimization plan and moving the real token for every
+    // transfer it produces where `debtor` is the payer, so settling doesn't
+    // require co-signing other members' unrelated transfers.
+    pub fn settle_all(env: Env, group_id: u32, debtor: Address) {
+        let mut group = Self::load_group(&env, group_id);
+        let token = Self::settlement_token(&env, group_id);
+
+        let plan = Self::compute_settlement_plan(env.clone(), group_id);
+        for transfer in plan.iter() {
+            if transfer.from == debtor {
+                Self::transfer_and_settle(
+                    &env,
+                    &mut group,
+                    &token,
+                    &transfer.from,
+                    &transfer.to,
+                    transfer.amount,
+                );
+            }
+        }
 
+        Self::save_group(&env, group_id, &group);
+    }
+
+    fn settlement_token(env: &Env, group_id: u32) -> Address {
+        env.storage()
+            .instance()
+            .get(&DataKey::SettlementToken(group_id))
+            .unwrap_or_else(|| panic!("Settlement token is not configured for this group"))
+    }
+
+    // Verify the debt, move the token from `from` to `to`, and only then
+    // update `member_shares` so the ledger never records value that didn't
+    // actually move on-chain.
+    fn transfer_and_settle(
+        env: &Env,
+        group: &mut Group,
+        token: &Address,
+        from: &Address,
+        to: &Address,
+        amount: i128,
+    ) {
         // Verify both addresses are members
-        if !group.members.contains(&from) {
+        if !group.members.contains(from) {
             panic!("From address is not a group member");
         }
-        if !group.members.contains(&to) {
+        if !group.members.contains(to) {
             panic!("To address is not a group member");
         }
+        if from == to {
+            panic!("Cannot settle debt with yourself");
+        }
 
         // Verify amount
         if amount <= 0 {
@@ -308,16 +692,104 @@ impl SplitPayment {
             panic!("Cannot settle more than what is owed");
         }
 
+        from.require_auth();
+        let token_client = token::Client::new(env, token);
+        token_client.transfer(from, to, &amount);
+
         // Update balances
         group.member_shares.set(from.clone(), from_share + amount);
         group.member_shares.set(to.clone(), to_share - amount);
+    }
+
+    // Compute the minimal set of transfers that zero out every member's balance
+    pub fn compute_settlement_plan(env: Env, group_id: u32) -> Vec<Transfer> {
+        let group = Self::load_group(&env, group_id);
+
+        let mut balances: Vec<(Address, i128)> = Vec::new(&env);
+        for member in group.members.iter() {
+            let balance = group.member_shares.get(member.clone()).unwrap_or(0);
+            if balance != 0 {
+                balances.push_back((member, balance));
+            }
+        }
+
+        // Guard against rounding drift: every expense's split covers its whole
+        // amount (10000 bps or exact amounts), but floor division in a bps
+        // split can leave the net of all balances off zero; refuse to settle then.
+        let net: i128 = balances.iter().map(|(_, balance)| balance).sum();
+        if net != 0 {
+            panic!("Member shares are not balanced");
+        }
+
+        let mut transfers: Vec<Transfer> = Vec::new(&env);
+
+        loop {
+            let mut creditor_idx: Option<u32> = None;
+            let mut debtor_idx: Option<u32> = None;
+
+            for i in 0..balances.len() {
+                let (_, balance) = balances.get(i).unwrap();
+                if balance > 0
+                    && (creditor_idx.is_none()
+                        || balance > balances.get(creditor_idx.unwrap()).unwrap().1)
+                {
+                    creditor_idx = Some(i);
+                }
+                if balance < 0
+                    && (debtor_idx.is_none()
+                        || balance < balances.get(debtor_idx.unwrap()).unwrap().1)
+                {
+                    debtor_idx = Some(i);
+                }
+            }
+
+            let (creditor_idx, debtor_idx) = match (creditor_idx, debtor_idx) {
+                (Some(c), Some(d)) => (c, d),
+                _ => break,
+            };
+
+            let (creditor, credit) = balances.get(creditor_idx).unwrap();
+            let (debtor, debt) = balances.get(debtor_idx).unwrap();
+
+            let settled = if credit < -debt { credit } else { -debt };
+
+            transfers.push_back(Transfer {
+                from: debtor.clone(),
+                to: creditor.clone(),
+                amount: settled,
+            });
+
+            let new_credit = credit - settled;
+            let new_debt = debt + settled;
+
+            balances.set(creditor_idx, (creditor, new_credit));
+            balances.set(debtor_idx, (debtor, new_debt));
 
-        env.storage().instance().set(&group_id, &group);
+            // Drop anyone who has reached zero, largest index first so the
+            // remaining indices stay valid.
+            if creditor_idx > debtor_idx {
+                if new_credit == 0 {
+                    balances.remove(creditor_idx);
+                }
+                if new_debt == 0 {
+                    balances.remove(debtor_idx);
+                }
+            } else {
+                if new_debt == 0 {
+                    balances.remove(debtor_idx);
+                }
+                if new_credit == 0 {
+                    balances.remove(creditor_idx);
+                }
+            }
+        }
+
+        transfers
     }
 
     // Get member balance
     pub fn get_member_balance(env: Env, group_id: u32, member: Address) -> i128 {
-        let group: Group = env.storage().instance().get(&group_id).unwrap();
+        let group = Self::load_group(&env, group_id);
         if !group.members.contains(&member) {
             panic!("Address is not a group member");
         }
@@ -326,9 +798,243 @@ impl SplitPayment {
 
     // Get group expenses
     pub fn get_group_expenses(env: Env, group_id: u32) -> Vec<Expense> {
-        env.storage()
-            .instance()
-            .get(&DataKey::GroupExpenses(group_id))
-            .unwrap_or(Vec::new(&env))
+        Self::load_expenses(&env, group_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use soroban_sdk::testutils::Address as _;
+
+    // Registers the contract, sets up an admin + a 1:1 base-asset conversion
+    // rate, and returns a client, three member addresses for a group, and
+    // the base asset.
+    fn setup(env: &Env) -> (SplitPaymentClient, Vec<Address>, Symbol) {
+        let contract_id = env.register_contract(None, SplitPayment);
+        let client = SplitPaymentClient::new(env, &contract_id);
+
+        let admin = Address::generate(env);
+        env.mock_all_auths();
+        client.initialize(&admin);
+
+        let asset = Symbol::new(env, "BASE");
+        client.set_conversion_rate(&asset, &10000);
+
+        let members = Vec::from_array(
+            env,
+            [
+                Address::generate(env),
+                Address::generate(env),
+                Address::generate(env),
+            ],
+        );
+
+        (client, members, asset)
+    }
+
+    // Regression test: an equal split of an amount not evenly divisible by
+    // the member count used to round-trip through basis points and lose
+    // value, leaving `member_shares` unbalanced and `compute_settlement_plan`
+    // panicking on a perfectly ordinary expense.
+    #[test]
+    fn equal_split_of_three_among_three_balances_to_zero() {
+        let env = Env::default();
+        let (client, members, asset) = setup(&env);
+        let payer = members.get(0).unwrap();
+        let group_id = client.create_group(&members);
+
+        client.add_expense_with_strategy(
+            &group_id,
+            &payer,
+            &3,
+            &asset,
+            &Symbol::new(&env, "dinner"),
+            &SplitStrategy::Equal,
+        );
+
+        let plan = client.compute_settlement_plan(&group_id);
+        let total: i128 = plan.iter().map(|t| t.amount).sum();
+        assert_eq!(total, 2);
+    }
+
+    #[test]
+    fn exact_split_balances_to_zero() {
+        let env = Env::default();
+        let (client, members, asset) = setup(&env);
+        let payer = members.get(0).unwrap();
+        let group_id = client.create_group(&members);
+
+        let amounts = Vec::from_array(&env, [1i128, 1, 1]);
+        client.add_expense_with_strategy(
+            &group_id,
+            &payer,
+            &3,
+            &asset,
+            &Symbol::new(&env, "dinner"),
+            &SplitStrategy::Exact(amounts),
+        );
+
+        // Should not panic: member_shares must be exactly balanced.
+        client.compute_settlement_plan(&group_id);
+    }
+
+    #[test]
+    fn shares_split_balances_to_zero() {
+        let env = Env::default();
+        let (client, members, asset) = setup(&env);
+        let payer = members.get(0).unwrap();
+        let group_id = client.create_group(&members);
+
+        let weights = Vec::from_array(
+            &env,
+            [
+                (members.get(0).unwrap(), 1u32),
+                (members.get(1).unwrap(), 1u32),
+                (members.get(2).unwrap(), 1u32),
+            ],
+        );
+        client.add_expense_with_strategy(
+            &group_id,
+            &payer,
+            &3,
+            &asset,
+            &Symbol::new(&env, "dinner"),
+            &SplitStrategy::Shares(weights),
+        );
+
+        // Should not panic: member_shares must be exactly balanced.
+        client.compute_settlement_plan(&group_id);
+    }
+
+    // Registers a Stellar asset contract to use as the settlement token.
+    fn create_token_contract<'a>(
+        env: &Env,
+        admin: &Address,
+    ) -> (token::Client<'a>, token::StellarAssetClient<'a>) {
+        let sac = env.register_stellar_asset_contract_v2(admin.clone());
+        (
+            token::Client::new(env, &sac.address()),
+            token::StellarAssetClient::new(env, &sac.address()),
+        )
+    }
+
+    #[test]
+    fn settle_debt_moves_the_real_token_and_clears_the_balance() {
+        let env = Env::default();
+        let (client, members, asset) = setup(&env);
+        let payer = members.get(0).unwrap();
+        let debtor = members.get(1).unwrap();
+        let group_id = client.create_group(&members);
+
+        client.add_expense_with_strategy(
+            &group_id,
+            &payer,
+            &3,
+            &asset,
+            &Symbol::new(&env, "dinner"),
+            &SplitStrategy::Equal,
+        );
+
+        let token_admin = Address::generate(&env);
+        let (token, token_admin_client) = create_token_contract(&env, &token_admin);
+        token_admin_client.mint(&debtor, &1);
+        client.init_settlement_token(&group_id, &token.address);
+
+        let owed = -client.get_member_balance(&group_id, &debtor);
+        client.settle_debt(&group_id, &debtor, &payer, &owed);
+
+        assert_eq!(token.balance(&debtor), 0);
+        assert_eq!(token.balance(&payer), 1);
+        assert_eq!(client.get_member_balance(&group_id, &debtor), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot settle debt with yourself")]
+    fn settle_debt_rejects_self_settlement() {
+        let env = Env::default();
+        let (client, members, asset) = setup(&env);
+        let payer = members.get(0).unwrap();
+        let group_id = client.create_group(&members);
+
+        client.add_expense_with_strategy(
+            &group_id,
+            &payer,
+            &3,
+            &asset,
+            &Symbol::new(&env, "dinner"),
+            &SplitStrategy::Equal,
+        );
+
+        let token_admin = Address::generate(&env);
+        let (token, _) = create_token_contract(&env, &token_admin);
+        client.init_settlement_token(&group_id, &token.address);
+
+        client.settle_debt(&group_id, &payer, &payer, &1);
+    }
+
+    // Registering a conversion rate for a non-base asset must convert the
+    // expense's `amount`/`member_shares` into base units, while preserving
+    // the raw `asset`/`original_amount` on the stored `Expense`.
+    #[test]
+    fn expense_in_non_base_asset_is_converted_to_base_units() {
+        let env = Env::default();
+        let (client, members, _base_asset) = setup(&env);
+        let payer = members.get(0).unwrap();
+        let group_id = client.create_group(&members);
+
+        // 1 EUR is worth 2 base units.
+        let eur = Symbol::new(&env, "EUR");
+        client.set_conversion_rate(&eur, &20000);
+
+        client.add_expense_with_strategy(
+            &group_id,
+            &payer,
+            &10,
+            &eur,
+            &Symbol::new(&env, "dinner"),
+            &SplitStrategy::Equal,
+        );
+
+        let expenses = client.get_group_expenses(&group_id);
+        let expense = expenses.get(0).unwrap();
+        assert_eq!(expense.asset, eur);
+        assert_eq!(expense.original_amount, 10);
+        assert_eq!(expense.amount, 20);
+
+        let plan = client.compute_settlement_plan(&group_id);
+        let total: i128 = plan.iter().map(|t| t.amount).sum();
+        assert_eq!(total, 12);
+    }
+
+    // `create_group` touches `MemberGroups` for every brand-new member, which
+    // must not panic just because the key was never written before.
+    #[test]
+    fn create_group_does_not_panic_for_brand_new_members() {
+        let env = Env::default();
+        let (client, members, _asset) = setup(&env);
+
+        let group_id = client.create_group(&members);
+        assert_eq!(client.get_group_members(&group_id), members);
+    }
+
+    #[test]
+    fn bump_group_does_not_panic_for_group_with_expenses() {
+        let env = Env::default();
+        let (client, members, asset) = setup(&env);
+        let payer = members.get(0).unwrap();
+        let group_id = client.create_group(&members);
+
+        client.add_expense_with_strategy(
+            &group_id,
+            &payer,
+            &3,
+            &asset,
+            &Symbol::new(&env, "dinner"),
+            &SplitStrategy::Equal,
+        );
+
+        // Should not panic, whether or not GroupExpenses has ever been bumped.
+        client.bump_group(&group_id, &GROUP_BUMP_AMOUNT);
     }
 }